@@ -1,8 +1,10 @@
 use aws_config::{Region, SdkConfig};
+use aws_sdk_cloudwatch::types::{Dimension, MetricDatum, StandardUnit};
 use aws_sdk_cloudwatchlogs::{types::{FilteredLogEvent, LogGroup}, Client};
+use chrono::{DateTime, Utc};
 use log::info;
 use crate::config::AppConfig;
-use crate::models::SendableError;
+use crate::models::{MetricRule, SendableError};
 
 lazy_static! {
     static ref AWS_REGIONS: Vec<&'static str> = vec![
@@ -66,6 +68,8 @@ pub async fn fetch_logs(
     log_group_name: &str,
     start_time: i64,
     end_time: i64,
+    filter_pattern: Option<&str>,
+    log_stream_name_prefix: Option<&str>,
 ) -> Result<Vec<FilteredLogEvent>, SendableError> {
     let mut result = Vec::new();
     let mut next_token = None;
@@ -76,6 +80,8 @@ pub async fn fetch_logs(
             .log_group_name(log_group_name)
             .set_start_time(Some(start_time))
             .set_end_time(Some(end_time))
+            .set_filter_pattern(filter_pattern.map(str::to_string))
+            .set_log_stream_name_prefix(log_stream_name_prefix.map(str::to_string))
             .set_next_token(next_token.clone())
             .send()
             .await?;
@@ -93,3 +99,47 @@ pub async fn fetch_logs(
 
     Ok(result)
 }
+
+pub async fn publish_metric_counts(
+    sdk_config: &SdkConfig,
+    namespace: &str,
+    log_group_name: &str,
+    metric_rules: &[MetricRule],
+    messages: &[String],
+    window_end: DateTime<Utc>,
+) -> Result<(), SendableError> {
+    if metric_rules.is_empty() {
+        return Ok(());
+    }
+
+    let timestamp = aws_smithy_types::DateTime::from_millis(window_end.timestamp_millis());
+
+    let datums = metric_rules
+        .iter()
+        .map(|rule| {
+            let count = messages.iter().filter(|m| m.contains(&rule.pattern)).count();
+            MetricDatum::builder()
+                .metric_name(&rule.name)
+                .value(count as f64)
+                .unit(StandardUnit::Count)
+                .timestamp(timestamp)
+                .dimensions(
+                    Dimension::builder()
+                        .name("LogGroup")
+                        .value(log_group_name)
+                        .build(),
+                )
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    let client = aws_sdk_cloudwatch::Client::new(sdk_config);
+    client
+        .put_metric_data()
+        .namespace(namespace)
+        .set_metric_data(Some(datums))
+        .send()
+        .await?;
+
+    Ok(())
+}
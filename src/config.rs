@@ -1,5 +1,6 @@
+use crate::models::MetricRule;
 use chrono::{DateTime, Utc};
-use clap::{command, Parser};
+use clap::{command, Parser, Subcommand};
 use log::error;
 use parse_duration::parse;
 
@@ -19,26 +20,105 @@ pub struct AppConfig {
     #[arg(long, default_value = "all")]
     pub log_groups: Option<Vec<String>>,
 
-    #[arg(long, default_value = "1h")]
-    pub duration: String,
+    #[arg(long)]
+    pub duration: Option<String>,
 
     #[arg(long, default_value = "logs.db")]
     pub sqlite_path: String,
+
+    #[arg(long)]
+    pub serve: bool,
+
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub bind_address: String,
+
+    #[arg(long)]
+    pub follow: bool,
+
+    #[arg(long, default_value = "30s")]
+    pub poll_interval: String,
+
+    #[arg(long)]
+    pub filter_pattern: Option<String>,
+
+    #[arg(long)]
+    pub log_stream_prefix: Option<String>,
+
+    #[arg(long, value_parser = parse_metric_rule)]
+    pub metric_rule: Vec<MetricRule>,
+
+    #[arg(long, default_value = "Analog/Logs")]
+    pub metric_namespace: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+fn parse_metric_rule(value: &str) -> Result<MetricRule, String> {
+    let mut name = None;
+    let mut pattern = None;
+
+    for segment in value.split(';') {
+        match segment.split_once('=') {
+            Some(("name", v)) => name = Some(v.to_string()),
+            Some(("pattern", v)) => pattern = Some(v.to_string()),
+            _ => return Err(format!("invalid metric rule segment '{}'", segment)),
+        }
+    }
+
+    match (name, pattern) {
+        (Some(name), Some(pattern)) => Ok(MetricRule { name, pattern }),
+        _ => Err(format!(
+            "metric rule '{}' must set both name=... and pattern=...",
+            value
+        )),
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    Export {
+        #[arg(long)]
+        output: Option<String>,
+    },
+    Import {
+        #[arg(long)]
+        input: Option<String>,
+    },
+    Serve,
 }
 
+const DEFAULT_FETCH_DURATION: &str = "1h";
+
 impl AppConfig {
     pub fn get_duration(&self) -> (DateTime<Utc>, DateTime<Utc>) {
-        // Parse duration string (e.g., "3h", "2days")
-        let duration = match parse(&self.duration) {
+        let duration_arg = self.duration.as_deref().unwrap_or(DEFAULT_FETCH_DURATION);
+        let duration = Self::parse_duration_arg(duration_arg);
+        let end_time = Utc::now();
+        let start_time = end_time - chrono::Duration::from_std(duration).unwrap();
+        (start_time, end_time)
+    }
+
+    // export has no fetch-oriented default: None means no time filter at all.
+    pub fn get_export_duration(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let duration = Self::parse_duration_arg(self.duration.as_deref()?);
+        let end_time = Utc::now();
+        let start_time = end_time - chrono::Duration::from_std(duration).unwrap();
+        Some((start_time, end_time))
+    }
+
+    pub fn get_poll_interval(&self) -> std::time::Duration {
+        Self::parse_duration_arg(&self.poll_interval)
+    }
+
+    // Parse a duration string (e.g., "3h", "2days", "30s").
+    fn parse_duration_arg(value: &str) -> std::time::Duration {
+        match parse(value) {
             Ok(d) => d,
             Err(e) => {
-                error!("Failed to parse duration '{}': {}", &self.duration, e);
+                error!("Failed to parse duration '{}': {}", value, e);
                 std::process::exit(1);
             }
-        };
-        let end_time = Utc::now();
-        let start_time = end_time - chrono::Duration::from_std(duration).unwrap();
-        (start_time, end_time)
+        }
     }
 }
-
@@ -1,7 +1,7 @@
 use std::ops::Deref;
 use std::sync::Arc;
-use chrono::Duration;
-use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Pool, Sqlite, SqlitePool};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Pool, Row, Sqlite, SqlitePool};
 use tokio::sync::Mutex;
 use crate::{config::AppConfig, models::SavedLogEvent};
 use crate::models::SendableError;
@@ -21,45 +21,161 @@ pub async fn init_connection(app_config: &AppConfig) -> Result<Pool<Sqlite>, Sen
     Ok(connection)
 }
 
-pub async fn init_sqlite_db(pool: &Arc<Mutex<Pool<Sqlite>>>) -> Result<(), SendableError> {
-    let locked_pool = pool.lock().await;
-    let pool_deref = locked_pool.deref();
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS cloudwatch_logs (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            log_group TEXT NOT NULL,
-            event_id TEXT,
-            timestamp INTEGER,
-            message TEXT
-        )
-        "#,
-    )
-    .execute(pool_deref)
-    .await?;
+pub const DB_VERSION: i64 = 3;
 
-    sqlx::query(
-        r#"
-        CREATE VIEW IF NOT EXISTS cloudwatch_unique_logs_view AS
-        SELECT 
-            log_group,
-            message,
-            COUNT(*) AS message_count
-        FROM 
-            cloudwatch_logs
-        GROUP BY 
-            log_group, message;
-        "#
-    ).execute(pool_deref)
-    .await?;
+async fn apply_migration(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    from_version: i64,
+) -> Result<(), SendableError> {
+    match from_version {
+        0 => {
+            sqlx::query(
+                r#"
+                CREATE TABLE cloudwatch_logs (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    log_group TEXT NOT NULL,
+                    event_id TEXT,
+                    timestamp INTEGER,
+                    message TEXT
+                )
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                CREATE VIEW cloudwatch_unique_logs_view AS
+                SELECT
+                    log_group,
+                    message,
+                    COUNT(*) AS message_count
+                FROM
+                    cloudwatch_logs
+                GROUP BY
+                    log_group, message;
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
 
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_log_group ON cloudwatch_logs (log_group)")
-        .execute(pool_deref)
+            sqlx::query("CREATE INDEX idx_log_group ON cloudwatch_logs (log_group)")
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query("CREATE INDEX idx_timestamp ON cloudwatch_logs (timestamp)")
+                .execute(&mut **tx)
+                .await?;
+
+            Ok(())
+        }
+        1 => {
+            sqlx::query(
+                r#"
+                CREATE TABLE ingest_checkpoints (
+                    log_group TEXT PRIMARY KEY,
+                    watermark INTEGER NOT NULL
+                )
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        2 => {
+            sqlx::query("ALTER TABLE cloudwatch_logs ADD COLUMN log_stream_name TEXT")
+                .execute(&mut **tx)
+                .await?;
+            sqlx::query("ALTER TABLE cloudwatch_logs ADD COLUMN ingestion_time INTEGER")
+                .execute(&mut **tx)
+                .await?;
+
+            sqlx::query(
+                r#"
+                CREATE VIRTUAL TABLE cloudwatch_logs_fts USING fts5(
+                    message,
+                    content = 'cloudwatch_logs',
+                    content_rowid = 'id'
+                )
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO cloudwatch_logs_fts(rowid, message)
+                SELECT id, message FROM cloudwatch_logs
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                CREATE TRIGGER cloudwatch_logs_ai AFTER INSERT ON cloudwatch_logs BEGIN
+                    INSERT INTO cloudwatch_logs_fts(rowid, message) VALUES (new.id, new.message);
+                END
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                CREATE TRIGGER cloudwatch_logs_ad AFTER DELETE ON cloudwatch_logs BEGIN
+                    INSERT INTO cloudwatch_logs_fts(cloudwatch_logs_fts, rowid, message) VALUES ('delete', old.id, old.message);
+                END
+                "#,
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        other => Err(format!("no migration defined for schema version {}", other).into()),
+    }
+}
+
+async fn get_user_version(tx: &mut sqlx::Transaction<'_, Sqlite>) -> Result<i64, SendableError> {
+    let (version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+        .fetch_one(&mut **tx)
         .await?;
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_timestamp ON cloudwatch_logs (timestamp)")
-        .execute(pool_deref)
+    Ok(version)
+}
+
+async fn set_user_version(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    version: i64,
+) -> Result<(), SendableError> {
+    // PRAGMA statements don't accept bound parameters, so the version is
+    // interpolated directly; it's always a value we computed ourselves.
+    sqlx::query(&format!("PRAGMA user_version = {}", version))
+        .execute(&mut **tx)
         .await?;
+    Ok(())
+}
+
+pub async fn init_sqlite_db(pool: &Arc<Mutex<Pool<Sqlite>>>) -> Result<(), SendableError> {
+    let locked_pool = pool.lock().await;
+    let mut tx = locked_pool.begin().await?;
+
+    let current_version = get_user_version(&mut tx).await?;
 
+    if current_version > DB_VERSION {
+        return Err(format!(
+            "database schema is at version {} but this binary only supports up to {}; refusing to run against a newer database",
+            current_version, DB_VERSION
+        )
+        .into());
+    }
+
+    for from_version in current_version..DB_VERSION {
+        apply_migration(&mut tx, from_version).await?;
+        set_user_version(&mut tx, from_version + 1).await?;
+    }
+
+    tx.commit().await?;
     Ok(())
 }
 
@@ -73,7 +189,7 @@ pub async fn dedupe_rows(
         WHERE id NOT IN (
             SELECT MIN(id)
             FROM cloudwatch_logs
-            GROUP BY log_group, event_id, timestamp, message
+            GROUP BY log_group, event_id, timestamp, message, log_stream_name
         );
     "#;
     sqlx::query(sql).execute(&mut tx).await?;
@@ -90,22 +206,24 @@ pub async fn store_events_in_sqlite(
     let mut tx = locked_pool.begin().await?;
 
     let insert_sql = r#"
-        INSERT INTO cloudwatch_logs (log_group, event_id, timestamp, message)
-        VALUES (?1, ?2, ?3, ?4)
+        INSERT INTO cloudwatch_logs (log_group, event_id, timestamp, message, log_stream_name, ingestion_time)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
     "#;
 
     for event in events {
         let event_id = event.event_id.clone().unwrap_or_default();
         let timestamp = event.timestamp.unwrap_or(0);
         let message = event.message.clone().unwrap_or_default();
-        let _log_stream_name = event.log_stream_name.clone().unwrap_or_default();
-        let _ingestion_time = event.ingestion_time.clone().unwrap_or_default();
+        let log_stream_name = event.log_stream_name.clone();
+        let ingestion_time = event.ingestion_time;
 
         sqlx::query(insert_sql)
             .bind(log_group_name)
             .bind(event_id)
             .bind(timestamp)
             .bind(message)
+            .bind(log_stream_name)
+            .bind(ingestion_time)
             .execute(&mut tx)
             .await?;
     }
@@ -113,3 +231,229 @@ pub async fn store_events_in_sqlite(
     tx.commit().await?;
     Ok(())
 }
+
+pub async fn export_events_jsonl(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    log_groups: &[String],
+    time_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    writer: &mut dyn std::io::Write,
+) -> Result<usize, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let filter_by_group = !log_groups.is_empty() && !log_groups.contains(&"all".to_string());
+
+    let mut sql =
+        String::from("SELECT log_group, event_id, timestamp, message, log_stream_name, ingestion_time FROM cloudwatch_logs");
+    let mut where_clauses: Vec<String> = Vec::new();
+    if time_range.is_some() {
+        where_clauses.push("timestamp BETWEEN ? AND ?".to_string());
+    }
+    if filter_by_group {
+        let placeholders = log_groups.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+        where_clauses.push(format!("log_group IN ({})", placeholders));
+    }
+    if !where_clauses.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&where_clauses.join(" AND "));
+    }
+    sql.push_str(" ORDER BY timestamp");
+
+    let mut query = sqlx::query(&sql);
+    if let Some((start_time, end_time)) = time_range {
+        query = query
+            .bind(start_time.timestamp_millis())
+            .bind(end_time.timestamp_millis());
+    }
+    if filter_by_group {
+        for log_group in log_groups {
+            query = query.bind(log_group);
+        }
+    }
+
+    let rows = query.fetch_all(pool_deref).await?;
+
+    let mut count = 0usize;
+    for row in rows {
+        let event = SavedLogEvent {
+            log_group: row.try_get("log_group").ok(),
+            log_stream_name: row.try_get("log_stream_name").ok(),
+            timestamp: row.try_get("timestamp").ok(),
+            message: row.try_get("message").ok(),
+            ingestion_time: row.try_get("ingestion_time").ok(),
+            event_id: row.try_get("event_id").ok(),
+        };
+        let line = serde_json::to_string(&event)?;
+        writeln!(writer, "{}", line)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+pub async fn list_log_groups(pool: &Arc<Mutex<Pool<Sqlite>>>) -> Result<Vec<String>, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let rows = sqlx::query("SELECT DISTINCT log_group FROM cloudwatch_logs ORDER BY log_group")
+        .fetch_all(pool_deref)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| row.get::<String, _>("log_group"))
+        .collect())
+}
+
+pub async fn query_logs(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    log_group: Option<&str>,
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SavedLogEvent>, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let mut sql = String::from(
+        "SELECT log_group, event_id, timestamp, message, log_stream_name, ingestion_time FROM cloudwatch_logs WHERE 1 = 1",
+    );
+    if log_group.is_some() {
+        sql.push_str(" AND log_group = ?");
+    }
+    if from.is_some() {
+        sql.push_str(" AND timestamp >= ?");
+    }
+    if to.is_some() {
+        sql.push_str(" AND timestamp <= ?");
+    }
+    sql.push_str(" ORDER BY timestamp LIMIT ? OFFSET ?");
+
+    let mut query = sqlx::query(&sql);
+    if let Some(log_group) = log_group {
+        query = query.bind(log_group);
+    }
+    if let Some(from) = from {
+        query = query.bind(from);
+    }
+    if let Some(to) = to {
+        query = query.bind(to);
+    }
+    query = query.bind(limit).bind(offset);
+
+    let rows = query.fetch_all(pool_deref).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SavedLogEvent {
+            log_group: row.try_get("log_group").ok(),
+            log_stream_name: row.try_get("log_stream_name").ok(),
+            timestamp: row.try_get("timestamp").ok(),
+            message: row.try_get("message").ok(),
+            ingestion_time: row.try_get("ingestion_time").ok(),
+            event_id: row.try_get("event_id").ok(),
+        })
+        .collect())
+}
+
+pub async fn query_unique_logs(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+) -> Result<Vec<crate::models::UniqueLogEntry>, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let rows = sqlx::query("SELECT log_group, message, message_count FROM cloudwatch_unique_logs_view")
+        .fetch_all(pool_deref)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| crate::models::UniqueLogEntry {
+            log_group: row.get("log_group"),
+            message: row.get("message"),
+            message_count: row.get("message_count"),
+        })
+        .collect())
+}
+
+pub async fn get_checkpoint(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    log_group: &str,
+) -> Result<Option<i64>, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let row = sqlx::query("SELECT watermark FROM ingest_checkpoints WHERE log_group = ?1")
+        .bind(log_group)
+        .fetch_optional(pool_deref)
+        .await?;
+
+    Ok(row.map(|row| row.get::<i64, _>("watermark")))
+}
+
+pub async fn set_checkpoint(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    log_group: &str,
+    watermark: i64,
+) -> Result<(), SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    sqlx::query(
+        r#"
+        INSERT INTO ingest_checkpoints (log_group, watermark)
+        VALUES (?1, ?2)
+        ON CONFLICT (log_group) DO UPDATE SET watermark = excluded.watermark
+        "#,
+    )
+    .bind(log_group)
+    .bind(watermark)
+    .execute(pool_deref)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn search_messages(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    log_group: Option<&str>,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SavedLogEvent>, SendableError> {
+    let locked_pool = pool.lock().await;
+    let pool_deref = locked_pool.deref();
+
+    let mut sql = String::from(
+        r#"
+        SELECT l.log_group, l.event_id, l.timestamp, l.message, l.log_stream_name, l.ingestion_time
+        FROM cloudwatch_logs_fts f
+        JOIN cloudwatch_logs l ON l.id = f.rowid
+        WHERE f.message MATCH ?
+        "#,
+    );
+    if log_group.is_some() {
+        sql.push_str(" AND l.log_group = ?");
+    }
+    sql.push_str(" ORDER BY rank LIMIT ?");
+
+    let mut sqlx_query = sqlx::query(&sql).bind(query);
+    if let Some(log_group) = log_group {
+        sqlx_query = sqlx_query.bind(log_group);
+    }
+    sqlx_query = sqlx_query.bind(limit);
+
+    let rows = sqlx_query.fetch_all(pool_deref).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SavedLogEvent {
+            log_group: row.try_get("log_group").ok(),
+            log_stream_name: row.try_get("log_stream_name").ok(),
+            timestamp: row.try_get("timestamp").ok(),
+            message: row.try_get("message").ok(),
+            ingestion_time: row.try_get("ingestion_time").ok(),
+            event_id: row.try_get("event_id").ok(),
+        })
+        .collect())
+}
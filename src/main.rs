@@ -5,17 +5,21 @@ mod aws;
 mod config;
 mod db;
 mod models;
+mod server;
 mod utilities;
 
 use crate::models::SendableError;
+use aws_config::SdkConfig;
 use aws_sdk_cloudwatchlogs::{types::LogGroup, Client};
 use chrono::{DateTime, Utc};
 use clap::Parser;
-use config::AppConfig;
+use config::{AppConfig, Command};
 use db::dedupe_rows;
 use log::{error, info};
-use models::SavedLogEvent;
+use models::{MetricRule, SavedLogEvent};
 use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::io::BufRead;
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::{Mutex, Semaphore};
@@ -52,6 +56,14 @@ async fn main() -> Result<(), SendableError> {
     let shared_pool = Arc::new(Mutex::new(pool));
     db::init_sqlite_db(&shared_pool).await?;
 
+    if let Some(command) = app_config.command.clone() {
+        return match command {
+            Command::Export { output } => run_export(&shared_pool, &app_config, output).await,
+            Command::Import { input } => run_import(&shared_pool, input).await,
+            Command::Serve => server::run_serve(Arc::clone(&shared_pool), &app_config.bind_address).await,
+        };
+    }
+
     let config = aws::build_config(&app_config).await?;
     let client = Client::new(&config);
     let all_log_groups = aws::get_log_groups(&client).await?;
@@ -72,14 +84,130 @@ async fn main() -> Result<(), SendableError> {
     let (start_time, end_time) = app_config.get_duration();
     info!("Fetching logs from {} to {}", start_time, end_time);
 
+    run_fetch_cycle(
+        &shared_pool,
+        &config,
+        &semaphore,
+        filtered_log_groups,
+        start_time,
+        end_time,
+        app_config.filter_pattern.as_deref(),
+        app_config.log_stream_prefix.as_deref(),
+        &app_config.metric_rule,
+        &app_config.metric_namespace,
+    )
+    .await?;
+
+    info!("Deduplicate log events");
+    dedupe_rows(&shared_pool).await?;
+
+    if app_config.follow {
+        // `--follow` never returns, so start `--serve` on its own task here.
+        if app_config.serve {
+            let serve_pool = Arc::clone(&shared_pool);
+            let bind_address = app_config.bind_address.clone();
+            tokio::spawn(async move {
+                if let Err(err) = server::run_serve(serve_pool, &bind_address).await {
+                    error!("HTTP query server exited: {}", err);
+                }
+            });
+        }
+
+        let log_groups_filter = match &app_config.log_groups {
+            Some(lg) => lg.clone(),
+            None => Vec::new(),
+        };
+        let poll_interval = app_config.get_poll_interval();
+        info!("Entering follow mode, polling every {:?}", poll_interval);
+
+        loop {
+            tokio::time::sleep(poll_interval).await;
+
+            let client = Client::new(&config);
+            let all_log_groups = match aws::get_log_groups(&client).await {
+                Ok(groups) => groups,
+                Err(err) => {
+                    error!("Failed to list log groups while polling: {}", err);
+                    continue;
+                }
+            };
+            let filtered_log_groups = filter_log_groups(all_log_groups, log_groups_filter.clone());
+            if filtered_log_groups.is_empty() {
+                continue;
+            }
+
+            let poll_end_time = Utc::now();
+            let cycle_result = run_fetch_cycle(
+                &shared_pool,
+                &config,
+                &semaphore,
+                filtered_log_groups,
+                start_time,
+                poll_end_time,
+                app_config.filter_pattern.as_deref(),
+                app_config.log_stream_prefix.as_deref(),
+                &app_config.metric_rule,
+                &app_config.metric_namespace,
+            )
+            .await;
+            if let Err(err) = cycle_result {
+                error!("Fetch cycle failed while polling: {}", err);
+                continue;
+            }
+
+            info!("Deduplicate log events");
+            if let Err(err) = dedupe_rows(&shared_pool).await {
+                error!("Failed to deduplicate log events while polling: {}", err);
+            }
+        }
+    }
+
+    info!("Done!");
+
+    if app_config.serve {
+        return server::run_serve(Arc::clone(&shared_pool), &app_config.bind_address).await;
+    }
+
+    Ok(())
+}
+
+async fn run_fetch_cycle(
+    shared_pool: &Arc<Mutex<Pool<Sqlite>>>,
+    config: &SdkConfig,
+    semaphore: &Arc<Semaphore>,
+    log_groups: Vec<LogGroup>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    filter_pattern: Option<&str>,
+    log_stream_prefix: Option<&str>,
+    metric_rules: &[MetricRule],
+    metric_namespace: &str,
+) -> Result<(), SendableError> {
     let mut join_handles = Vec::new();
-    for log_group in filtered_log_groups {
-        let shared_pool = Arc::clone(&shared_pool);
-        let permit = Arc::clone(&semaphore);
-        let client = Client::new(&config);
-        let handle: tokio::task::JoinHandle<Result<(), SendableError>> = tokio::spawn( async move {
+    for log_group in log_groups {
+        let shared_pool = Arc::clone(shared_pool);
+        let permit = Arc::clone(semaphore);
+        let sdk_config = config.clone();
+        let client = Client::new(config);
+        let filter_pattern = filter_pattern.map(str::to_string);
+        let log_stream_prefix = log_stream_prefix.map(str::to_string);
+        let metric_rules = metric_rules.to_vec();
+        let metric_namespace = metric_namespace.to_string();
+        let handle: tokio::task::JoinHandle<Result<(), SendableError>> = tokio::spawn(async move {
             let _permit = permit.acquire().await?;
-            process_one_log_group(&shared_pool, client, start_time, end_time, log_group).await?;
+            process_one_log_group(
+                &shared_pool,
+                client,
+                &sdk_config,
+                start_time,
+                end_time,
+                log_group,
+                filter_pattern.as_deref(),
+                log_stream_prefix.as_deref(),
+                &metric_rules,
+                &metric_namespace,
+            )
+            .await?;
             Ok(())
         });
         join_handles.push(handle);
@@ -90,28 +218,45 @@ async fn main() -> Result<(), SendableError> {
         res.0?;
     }
 
-    info!("Deduplicate log events");
-    dedupe_rows(&shared_pool).await?;
-
-    info!("Done!");
     Ok(())
 }
 
+// Safety margin behind the latest observed event timestamp, to tolerate
+// CloudWatch Logs ingestion lag when setting the watermark.
+const INGESTION_LAG_SAFETY_MARGIN_MILLIS: i64 = 5 * 60 * 1000;
+
 async fn process_one_log_group(
     pool: &Arc<Mutex<Pool<Sqlite>>>,
     client: Client,
+    sdk_config: &SdkConfig,
     start_time: DateTime<Utc>,
     end_time: DateTime<Utc>,
     log_group: LogGroup,
+    filter_pattern: Option<&str>,
+    log_stream_prefix: Option<&str>,
+    metric_rules: &[MetricRule],
+    metric_namespace: &str,
 ) -> Result<(), SendableError> {
     let group_name = log_group.log_group_name.clone().unwrap_or_default();
-    info!("Retrieving events for log group: {}", group_name);
+
+    // Never fetch earlier than this group's stored watermark.
+    let fetch_start_time = match db::get_checkpoint(pool, &group_name).await? {
+        Some(watermark) => std::cmp::max(start_time, utilities::millis_to_datetime(watermark + 1)),
+        None => start_time,
+    };
+
+    info!(
+        "Retrieving events for log group: {} from {}",
+        group_name, fetch_start_time
+    );
 
     let events = aws::fetch_logs(
         &client,
         &group_name,
-        start_time.timestamp_millis(),
+        fetch_start_time.timestamp_millis(),
         end_time.timestamp_millis(),
+        filter_pattern,
+        log_stream_prefix,
     )
     .await;
 
@@ -123,6 +268,7 @@ async fn process_one_log_group(
     let mapped_events = events
         .into_iter()
         .map(|x| SavedLogEvent {
+            log_group: Some(group_name.clone()),
             log_stream_name: x.log_stream_name,
             timestamp: x.timestamp,
             message: x.message,
@@ -143,7 +289,34 @@ async fn process_one_log_group(
             "Failed to store events for log group '{}': {}",
             group_name, err
         );
+        // Don't advance the checkpoint past events that never landed.
+        return Ok(());
     }
+
+    // Watermark on the latest event seen, not on `end_time`, to tolerate
+    // CloudWatch ingestion lag.
+    let latest_event_timestamp = mapped_events.iter().filter_map(|event| event.timestamp).max();
+    if let Some(latest_event_timestamp) = latest_event_timestamp {
+        let watermark = latest_event_timestamp - INGESTION_LAG_SAFETY_MARGIN_MILLIS;
+        db::set_checkpoint(pool, &group_name, watermark).await?;
+    }
+
+    if !metric_rules.is_empty() {
+        let messages = mapped_events
+            .iter()
+            .filter_map(|event| event.message.clone())
+            .collect::<Vec<String>>();
+        aws::publish_metric_counts(
+            sdk_config,
+            metric_namespace,
+            &group_name,
+            metric_rules,
+            &messages,
+            end_time,
+        )
+        .await?;
+    }
+
     Ok(())
 }
 
@@ -164,3 +337,73 @@ fn filter_log_groups(all_log_groups: Vec<LogGroup>, log_groups: Vec<String>) ->
     };
     filtered_log_groups
 }
+
+async fn run_export(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    app_config: &AppConfig,
+    output: Option<String>,
+) -> Result<(), SendableError> {
+    let log_groups = app_config.log_groups.clone().unwrap_or_default();
+    let time_range = app_config.get_export_duration();
+
+    let mut writer: Box<dyn std::io::Write> = match output {
+        Some(path) => Box::new(std::fs::File::create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let count = db::export_events_jsonl(pool, &log_groups, time_range, &mut writer).await?;
+    info!("Exported {} event(s)", count);
+    Ok(())
+}
+
+const IMPORT_BATCH_SIZE: usize = 500;
+
+async fn run_import(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    input: Option<String>,
+) -> Result<(), SendableError> {
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+        None => Box::new(std::io::BufReader::new(std::io::stdin())),
+    };
+
+    let mut batch: Vec<SavedLogEvent> = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut total = 0usize;
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        batch.push(serde_json::from_str(&line)?);
+        if batch.len() >= IMPORT_BATCH_SIZE {
+            total += import_batch(pool, &batch).await?;
+            batch.clear();
+        }
+    }
+    if !batch.is_empty() {
+        total += import_batch(pool, &batch).await?;
+    }
+
+    info!("Imported {} event(s)", total);
+    info!("Deduplicate log events");
+    dedupe_rows(pool).await?;
+    Ok(())
+}
+
+async fn import_batch(
+    pool: &Arc<Mutex<Pool<Sqlite>>>,
+    batch: &[SavedLogEvent],
+) -> Result<usize, SendableError> {
+    let mut by_group: HashMap<String, Vec<SavedLogEvent>> = HashMap::new();
+    for event in batch {
+        let log_group = event.log_group.clone().unwrap_or_default();
+        by_group.entry(log_group).or_default().push(event.clone());
+    }
+
+    for (log_group, events) in &by_group {
+        db::store_events_in_sqlite(pool, log_group, events).await?;
+    }
+
+    Ok(batch.len())
+}
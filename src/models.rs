@@ -1,9 +1,26 @@
+use serde::{Deserialize, Serialize};
+
 pub type SendableError = Box<dyn std::error::Error + Send + Sync>;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedLogEvent {
+    pub log_group: Option<String>,
     pub log_stream_name: Option<String>,
     pub timestamp: Option<i64>,
     pub message: Option<String>,
     pub ingestion_time: Option<i64>,
     pub event_id: Option<String>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UniqueLogEntry {
+    pub log_group: String,
+    pub message: String,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricRule {
+    pub name: String,
+    pub pattern: String,
+}
@@ -0,0 +1,109 @@
+use crate::models::SendableError;
+use crate::{db, models::SavedLogEvent};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use log::info;
+use serde::Deserialize;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+struct ServerState {
+    pool: Arc<Mutex<Pool<Sqlite>>>,
+}
+
+struct ApiError(SendableError);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0.to_string()).into_response()
+    }
+}
+
+impl From<SendableError> for ApiError {
+    fn from(error: SendableError) -> Self {
+        ApiError(error)
+    }
+}
+
+pub async fn run_serve(
+    pool: Arc<Mutex<Pool<Sqlite>>>,
+    bind_address: &str,
+) -> Result<(), SendableError> {
+    let state = ServerState { pool };
+    let app = Router::new()
+        .route("/log-groups", get(list_log_groups))
+        .route("/logs", get(list_logs))
+        .route("/unique", get(list_unique))
+        .route("/search", get(search_messages))
+        .with_state(state);
+
+    info!("Serving stored logs on http://{}", bind_address);
+    let listener = tokio::net::TcpListener::bind(bind_address).await?;
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn list_log_groups(State(state): State<ServerState>) -> Result<Json<Vec<String>>, ApiError> {
+    let log_groups = db::list_log_groups(&state.pool).await?;
+    Ok(Json(log_groups))
+}
+
+#[derive(Debug, Deserialize)]
+struct LogsParams {
+    log_group: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+async fn list_logs(
+    State(state): State<ServerState>,
+    Query(params): Query<LogsParams>,
+) -> Result<Json<Vec<SavedLogEvent>>, ApiError> {
+    let events = db::query_logs(
+        &state.pool,
+        params.log_group.as_deref(),
+        params.from,
+        params.to,
+        params.limit.unwrap_or(100),
+        params.offset.unwrap_or(0),
+    )
+    .await?;
+    Ok(Json(events))
+}
+
+async fn list_unique(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<crate::models::UniqueLogEntry>>, ApiError> {
+    let entries = db::query_unique_logs(&state.pool).await?;
+    Ok(Json(entries))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    log_group: Option<String>,
+    q: String,
+    limit: Option<i64>,
+}
+
+async fn search_messages(
+    State(state): State<ServerState>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Vec<SavedLogEvent>>, ApiError> {
+    let events = db::search_messages(
+        &state.pool,
+        params.log_group.as_deref(),
+        &params.q,
+        params.limit.unwrap_or(100),
+    )
+    .await?;
+    Ok(Json(events))
+}